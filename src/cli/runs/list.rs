@@ -0,0 +1,38 @@
+use clap::Parser;
+use humantime::format_duration;
+
+use super::run_store::list_runs;
+use crate::Project;
+
+/// List all the detached runs of the project, along with their live status.
+#[derive(Debug, Parser)]
+pub struct Args {}
+
+pub async fn execute(project: Project, _args: Args) -> miette::Result<()> {
+    let runs = list_runs(&project)?;
+
+    if runs.is_empty() {
+        eprintln!("No detached runs found.");
+        return Ok(());
+    }
+
+    for run in runs {
+        let status = if run.is_running() {
+            let elapsed = format_duration(run.run_duration());
+            format!("running (pid {}, {elapsed} elapsed)", run.pid)
+        } else {
+            let exit_code = run
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            let ago = run
+                .since_finished()
+                .map(|d| format!("{} ago", format_duration(d)))
+                .unwrap_or_else(|| "at an unknown time".to_string());
+            format!("exited (code {exit_code}, {ago})")
+        };
+        println!("{:<20} {:<12} {}", run.id, status, run.command);
+    }
+
+    Ok(())
+}
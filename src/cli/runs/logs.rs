@@ -0,0 +1,66 @@
+use std::{io::Read, time::Duration};
+
+use clap::Parser;
+use miette::IntoDiagnostic;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use super::run_store::get_run;
+use crate::Project;
+
+/// How often to poll the log file for new data while `--follow`ing.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Show the captured output of a detached run.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The id of the run, as shown by `pixi runs list`.
+    pub id: String,
+
+    /// Keep streaming new output as it's produced, like `tail -f`, until the
+    /// run exits or the command is interrupted.
+    #[clap(long, short = 'f')]
+    pub follow: bool,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let Some(run) = get_run(&project, &args.id)? else {
+        miette::bail!("No detached run found with id '{}'", args.id);
+    };
+
+    let log_path = run.log_path(&project);
+    let mut file = std::fs::File::open(&log_path).into_diagnostic()?;
+    let mut stdout = BufWriter::new(tokio::io::stdout());
+
+    loop {
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk).into_diagnostic()?;
+        if !chunk.is_empty() {
+            stdout.write_all(&chunk).await.into_diagnostic()?;
+            stdout.flush().await.into_diagnostic()?;
+        }
+
+        if !args.follow {
+            break;
+        }
+        if !run.is_running() {
+            // Drain whatever was written between our last read and the
+            // process exiting, then stop.
+            let mut remainder = Vec::new();
+            file.read_to_end(&mut remainder).into_diagnostic()?;
+            if !remainder.is_empty() {
+                stdout.write_all(&remainder).await.into_diagnostic()?;
+                stdout.flush().await.into_diagnostic()?;
+            }
+            break;
+        }
+
+        // `read_to_end` leaves the cursor at whatever it managed to read, so
+        // the next iteration picks up exactly where this one left off.
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_POLL_INTERVAL) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    Ok(())
+}
@@ -9,6 +9,7 @@ mod kill;
 mod kill_all;
 mod list;
 mod logs;
+mod run_store;
 
 #[derive(Debug, Parser)]
 pub enum Command {
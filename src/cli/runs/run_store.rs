@@ -0,0 +1,396 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    process::Stdio,
+    time::{Duration, SystemTime},
+};
+
+use miette::IntoDiagnostic;
+use serde::{Deserialize, Serialize};
+
+use crate::Project;
+
+/// Metadata that is persisted for every detached run (`pixi run --detach`) so
+/// that it can still be inspected (`pixi runs list`, `pixi runs logs`, ...)
+/// after the shell that started it has exited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RunRecord {
+    /// Unique identifier for the run, also the name of its directory under
+    /// [`runs_dir`].
+    pub(crate) id: String,
+    /// The pid of the detached process.
+    pub(crate) pid: u32,
+    /// The kernel start time of `pid`, in clock ticks since boot (field 22 of
+    /// `/proc/<pid>/stat`), captured at spawn time. `None` for records
+    /// written before this field existed, or on platforms where it can't be
+    /// read. Together with `pid` this uniquely identifies "our" process even
+    /// after the OS recycles the pid onto an unrelated one; see
+    /// [`process_start_ticks`].
+    pub(crate) start_ticks: Option<u64>,
+    /// The original command line that was executed.
+    pub(crate) command: String,
+    /// When the run was started.
+    pub(crate) started_at: SystemTime,
+    /// When the run finished, filled in by [`mark_finished`] once the
+    /// spawning code reaps the child. `None` while the run is still going
+    /// (or if pixi never got a chance to observe the exit, e.g. the pixi
+    /// process supervising it was killed).
+    pub(crate) finished_at: Option<SystemTime>,
+    /// The process's exit code, filled in alongside `finished_at`.
+    pub(crate) exit_code: Option<i32>,
+}
+
+impl RunRecord {
+    /// Path to the file that captures the combined stdout/stderr of this run.
+    pub(crate) fn log_path(&self, project: &Project) -> PathBuf {
+        run_dir(&runs_dir(project), &self.id).join("output.log")
+    }
+
+    fn record_path(runs_dir: &std::path::Path, id: &str) -> PathBuf {
+        run_dir(runs_dir, id).join("run.json")
+    }
+
+    fn load(runs_dir: &std::path::Path, id: &str) -> io::Result<Self> {
+        let contents = fs::read_to_string(Self::record_path(runs_dir, id))?;
+        serde_json::from_str(&contents)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn save(&self, runs_dir: &std::path::Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let dir = run_dir(runs_dir, &self.id);
+        fs::create_dir_all(&dir)?;
+        fs::write(Self::record_path(runs_dir, &self.id), contents)
+    }
+
+    /// Returns whether the process behind this run is still alive.
+    ///
+    /// If [`Self::finished_at`] is set, that's authoritative (it's only ever
+    /// written once [`spawn_detached`] has actually reaped the child) and we
+    /// trust it outright. Otherwise this falls back to comparing the pid's
+    /// *current* kernel start time against [`Self::start_ticks`], the one we
+    /// captured when we spawned it: a bare "does `/proc/<pid>` exist" check
+    /// would happily report an unrelated process that the OS later recycled
+    /// our old pid onto as our run still running, whereas the (pid, start
+    /// time) pair the kernel hands out is never reused.
+    pub(crate) fn is_running(&self) -> bool {
+        if self.finished_at.is_some() {
+            return false;
+        }
+
+        let Some(current_ticks) = process_start_ticks(self.pid) else {
+            // No (or unparsable) `/proc/<pid>/stat`: the process is gone, or
+            // we're on a platform where we can't check at all, in which case
+            // we conservatively report "not running" rather than claim a
+            // liveness we can't verify.
+            return false;
+        };
+        match self.start_ticks {
+            Some(recorded) => recorded == current_ticks,
+            // Record predates this field: fall back to the weaker bare
+            // pid-liveness check rather than always reporting "finished".
+            None => true,
+        }
+    }
+
+    /// How long the run has been (or was) going, from start to finish (or
+    /// "now", if it's still running).
+    pub(crate) fn run_duration(&self) -> Duration {
+        let end = self.finished_at.unwrap_or(SystemTime::now());
+        end.duration_since(self.started_at).unwrap_or_default()
+    }
+
+    /// How long ago the run finished, if it has.
+    pub(crate) fn since_finished(&self) -> Option<Duration> {
+        self.finished_at.map(|finished_at| finished_at.elapsed().unwrap_or_default())
+    }
+}
+
+/// Reads the kernel's process start time for `pid` (field 22 of
+/// `/proc/<pid>/stat`, in clock ticks since boot), if it's currently running.
+///
+/// The kernel assigns this once, when the pid is created, and it never
+/// changes for that process's lifetime: two different processes can later
+/// share a pid (the OS recycles them), but never a `(pid, start_ticks)` pair.
+/// That makes it the standard way to tell "the process we spawned" apart
+/// from an unrelated one the OS has since handed the same pid to, unlike a
+/// bare `/proc/<pid>` existence check.
+#[cfg(target_os = "linux")]
+fn process_start_ticks(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // Field 2 is `(comm)`, the executable name in parens, which may itself
+    // contain spaces or parens; skip past the *last* `)` before splitting on
+    // whitespace so we don't misalign the remaining fields on such names.
+    let after_comm = stat.rsplit_once(')')?.1;
+    after_comm.split_whitespace().nth(19)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_start_ticks(_pid: u32) -> Option<u64> {
+    // We don't have a lightweight, dependency-free way to read this on other
+    // platforms here; `is_running` treats "unknown" as "not running".
+    None
+}
+
+/// Spawns `command` as a detached run tracked under `id`, persists its
+/// [`RunRecord`] immediately (so `pixi runs list`/`logs` can see it right
+/// away), then blocks until it exits and records the result via
+/// [`mark_finished`].
+///
+/// This *is* "wherever the detached child is reaped": capturing an exit code
+/// requires being the process's real parent, which only the process that
+/// called [`std::process::Command::spawn`] can be, so the reaping has to
+/// happen here rather than in a later, separate `pixi runs list`/`logs`
+/// invocation. It's meant to run inside the backgrounded/`setsid` process
+/// that `pixi run --detach` forks off; that fork/daemonize step itself lives
+/// in the `run` command, outside the `runs` management subtree this module
+/// belongs to.
+pub(crate) fn spawn_detached(
+    project: &Project,
+    id: &str,
+    command_display: &str,
+    command: &mut std::process::Command,
+) -> miette::Result<RunRecord> {
+    spawn_detached_in(&runs_dir(project), id, command_display, command)
+}
+
+fn spawn_detached_in(
+    runs_dir: &std::path::Path,
+    id: &str,
+    command_display: &str,
+    command: &mut std::process::Command,
+) -> miette::Result<RunRecord> {
+    let dir = run_dir(runs_dir, id);
+    fs::create_dir_all(&dir).into_diagnostic()?;
+    let log = fs::File::create(dir.join("output.log")).into_diagnostic()?;
+
+    let mut child = command
+        .stdout(Stdio::from(log.try_clone().into_diagnostic()?))
+        .stderr(Stdio::from(log))
+        .spawn()
+        .into_diagnostic()?;
+
+    let record = RunRecord {
+        id: id.to_string(),
+        pid: child.id(),
+        start_ticks: process_start_ticks(child.id()),
+        command: command_display.to_string(),
+        started_at: SystemTime::now(),
+        finished_at: None,
+        exit_code: None,
+    };
+    record.save(runs_dir).into_diagnostic()?;
+
+    let status = child.wait().into_diagnostic()?;
+    mark_finished_in(runs_dir, id, status.code().unwrap_or(-1))?;
+
+    get_run_in(runs_dir, id)?
+        .ok_or_else(|| miette::miette!("run record for '{id}' disappeared while it was running"))
+}
+
+/// Persists the exit code of a detached run once its supervising process has
+/// reaped the child, so that `is_running`/`pixi runs list` don't have to
+/// infer completion from a pid that the OS is free to recycle.
+///
+/// Called by [`spawn_detached`] right after `Child::wait()` returns.
+pub(crate) fn mark_finished(project: &Project, id: &str, exit_code: i32) -> miette::Result<()> {
+    mark_finished_in(&runs_dir(project), id, exit_code)
+}
+
+fn mark_finished_in(
+    runs_dir: &std::path::Path,
+    id: &str,
+    exit_code: i32,
+) -> miette::Result<()> {
+    let mut record = RunRecord::load(runs_dir, id).into_diagnostic()?;
+    record.finished_at = Some(SystemTime::now());
+    record.exit_code = Some(exit_code);
+    record.save(runs_dir).into_diagnostic()
+}
+
+/// Returns the directory that stores the state of all detached runs for the
+/// given project.
+pub(crate) fn runs_dir(project: &Project) -> PathBuf {
+    project.pixi_dir().join("run_logs")
+}
+
+fn run_dir(runs_dir: &std::path::Path, id: &str) -> PathBuf {
+    runs_dir.join(id)
+}
+
+/// Lists the records of all detached runs that are currently tracked for the
+/// project, most recently started first.
+pub(crate) fn list_runs(project: &Project) -> miette::Result<Vec<RunRecord>> {
+    list_runs_in(&runs_dir(project))
+}
+
+fn list_runs_in(runs_dir: &std::path::Path) -> miette::Result<Vec<RunRecord>> {
+    if !runs_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut runs = Vec::new();
+    for entry in fs::read_dir(runs_dir).into_diagnostic()? {
+        let entry = entry.into_diagnostic()?;
+        let Some(id) = entry.file_name().to_str().map(str::to_owned) else {
+            continue;
+        };
+        if let Ok(record) = RunRecord::load(runs_dir, &id) {
+            runs.push(record);
+        }
+    }
+    runs.sort_by_key(|run| std::cmp::Reverse(run.started_at));
+    Ok(runs)
+}
+
+/// Looks up a single run by id.
+pub(crate) fn get_run(project: &Project, id: &str) -> miette::Result<Option<RunRecord>> {
+    get_run_in(&runs_dir(project), id)
+}
+
+fn get_run_in(runs_dir: &std::path::Path, id: &str) -> miette::Result<Option<RunRecord>> {
+    match RunRecord::load(runs_dir, id) {
+        Ok(record) => Ok(Some(record)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).into_diagnostic(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn temp_runs_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pixi-runs-store-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_record(runs_dir: &std::path::Path, record: &RunRecord) {
+        record.save(runs_dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_runs_empty_when_dir_missing() {
+        let dir = std::env::temp_dir().join("pixi-runs-store-test-does-not-exist");
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(list_runs_in(&dir).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_list_runs_orders_most_recent_first() {
+        let dir = temp_runs_dir("ordering");
+        let now = SystemTime::now();
+
+        write_record(
+            &dir,
+            &RunRecord {
+                id: "older".to_string(),
+                pid: 1,
+                start_ticks: None,
+                command: "echo older".to_string(),
+                started_at: now - Duration::from_secs(60),
+                finished_at: None,
+                exit_code: None,
+            },
+        );
+        write_record(
+            &dir,
+            &RunRecord {
+                id: "newer".to_string(),
+                pid: 2,
+                start_ticks: None,
+                command: "echo newer".to_string(),
+                started_at: now,
+                finished_at: None,
+                exit_code: None,
+            },
+        );
+
+        let runs = list_runs_in(&dir).unwrap();
+        let ids = runs.iter().map(|run| run.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["newer", "older"]);
+    }
+
+    #[test]
+    fn test_get_run_missing_returns_none() {
+        let dir = temp_runs_dir("missing");
+        assert!(get_run_in(&dir, "no-such-run").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_mark_finished_sets_exit_code_and_stops_reporting_running() {
+        let dir = temp_runs_dir("mark-finished");
+        write_record(
+            &dir,
+            &RunRecord {
+                id: "run-1".to_string(),
+                pid: std::process::id(),
+                start_ticks: None,
+                command: "echo hi".to_string(),
+                started_at: SystemTime::now(),
+                finished_at: None,
+                exit_code: None,
+            },
+        );
+
+        mark_finished_in(&dir, "run-1", 7).unwrap();
+
+        let record = get_run_in(&dir, "run-1").unwrap().unwrap();
+        assert_eq!(record.exit_code, Some(7));
+        assert!(!record.is_running());
+        assert!(record.since_finished().is_some());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_spawn_detached_records_exit_code_on_completion() {
+        let dir = temp_runs_dir("spawn-detached");
+
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "echo hello; exit 3"]);
+        let record = spawn_detached_in(&dir, "run-1", "sh -c 'echo hello; exit 3'", &mut command)
+            .unwrap();
+
+        assert_eq!(record.exit_code, Some(3));
+        assert!(record.finished_at.is_some());
+        assert!(!record.is_running());
+
+        let log = fs::read_to_string(run_dir(&dir, "run-1").join("output.log")).unwrap();
+        assert_eq!(log, "hello\n");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_process_start_ticks_is_stable_for_the_current_process() {
+        let pid = std::process::id();
+        let first = process_start_ticks(pid).expect("own pid should be readable");
+        let second = process_start_ticks(pid).expect("own pid should still be readable");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_is_running_false_for_recorded_pid_with_mismatching_start_ticks() {
+        // `pid` is our own, very-much-alive pid, but `start_ticks` is a value
+        // that can never match it: this simulates the OS having recycled
+        // this pid onto an unrelated process since we recorded it.
+        let record = RunRecord {
+            id: "run-1".to_string(),
+            pid: std::process::id(),
+            start_ticks: Some(u64::MAX),
+            command: "echo hi".to_string(),
+            started_at: SystemTime::now(),
+            finished_at: None,
+            exit_code: None,
+        };
+
+        assert!(!record.is_running());
+    }
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use itertools::Itertools;
 use miette::Diagnostic;
@@ -8,24 +11,265 @@ use pixi_default_versions::{
 use pixi_manifest::{FeaturesExt, LibCSystemRequirement, SystemRequirements};
 use rattler_conda_types::{GenericVirtualPackage, Platform, Version};
 use rattler_virtual_packages::{
-    Archspec, Cuda, DetectVirtualPackageError, LibC, Linux, Osx, VirtualPackage,
+    Archspec, Cuda, DetectVirtualPackageError, LibC, Linux, Osx, Override, VirtualPackage,
     VirtualPackageOverrides,
 };
 use thiserror::Error;
 
 use crate::workspace::{errors::UnsupportedPlatformError, Environment};
 
+/// Combines a manifest-side override (e.g. an eventual `pixi.toml`
+/// `[target.<platform>.system-requirements-override]` table) with the
+/// environment-side override (`CONDA_OVERRIDE_*`), with the environment
+/// taking precedence field-by-field. A field left at `Override::Default` on
+/// the environment side falls through to whatever the manifest specified,
+/// which in turn falls through to the regular manifest/default resolution in
+/// the `resolve_*` functions below.
+fn combine_overrides(
+    manifest: VirtualPackageOverrides,
+    env: VirtualPackageOverrides,
+) -> VirtualPackageOverrides {
+    fn pick<T>(env: Override<T>, manifest: Override<T>) -> Override<T> {
+        match env {
+            Override::Default => manifest,
+            explicit => explicit,
+        }
+    }
+
+    VirtualPackageOverrides {
+        osx: pick(env.osx, manifest.osx),
+        libc: pick(env.libc, manifest.libc),
+        cuda: pick(env.cuda, manifest.cuda),
+        archspec: pick(env.archspec, manifest.archspec),
+    }
+}
+
+/// Reads the `PT_INTERP` program header of a little-endian 64-bit ELF binary,
+/// i.e. the path to the dynamic loader it was linked against. This is the
+/// authoritative way to tell a glibc binary (interpreter under
+/// `/lib64/ld-linux-*.so.2`) from a musl one (interpreter under
+/// `.../ld-musl-*.so.1`) directly from the binary itself, rather than
+/// guessing from well-known library directories: the latter both
+/// false-positives (an unrelated musl toolchain package installed alongside
+/// glibc) and false-negatives (non-FHS distributions, e.g. Nix-based ones,
+/// don't keep loaders under fixed paths at all).
+fn elf_interpreter(path: &Path) -> Option<PathBuf> {
+    let data = std::fs::read(path).ok()?;
+    if data.len() < 64 || &data[0..4] != b"\x7fELF" {
+        return None;
+    }
+    let is_64_bit = data[4] == 2;
+    let is_little_endian = data[5] == 1;
+    if !is_64_bit || !is_little_endian {
+        // Only the little-endian 64-bit layout pixi itself is built for is
+        // supported; anything else just means we can't detect musl this way.
+        return None;
+    }
+
+    let read_u16 = |offset: usize| -> Option<u16> {
+        data.get(offset..offset + 2)
+            .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+    };
+    let read_u64 = |offset: usize| -> Option<u64> {
+        data.get(offset..offset + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    };
+
+    const PT_INTERP: u32 = 3;
+    let e_phoff = read_u64(0x20)? as usize;
+    let e_phentsize = read_u16(0x36)? as usize;
+    let e_phnum = read_u16(0x38)? as usize;
+
+    for i in 0..e_phnum {
+        let header = e_phoff + i * e_phentsize;
+        let p_type = data
+            .get(header..header + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))?;
+        if p_type != PT_INTERP {
+            continue;
+        }
+
+        let p_offset = read_u64(header + 8)? as usize;
+        let p_filesz = read_u64(header + 32)? as usize;
+        let bytes = data.get(p_offset..p_offset + p_filesz)?;
+        let interp = std::str::from_utf8(bytes).ok()?.trim_end_matches('\0');
+        return Some(PathBuf::from(interp));
+    }
+
+    None
+}
+
+/// A handful of binaries that are virtually always present on a Linux host
+/// and dynamically linked against whatever libc the host actually provides.
+///
+/// Deliberately not `/proc/self/exe`: pixi's own Linux release binaries are
+/// commonly distributed as statically-linked musl builds precisely so one
+/// binary runs unmodified on both glibc and musl hosts. Such a static binary
+/// has no `PT_INTERP` segment at all, so reading pixi's own interpreter would
+/// report "no interpreter" (and therefore "assume glibc") on every host,
+/// including musl ones running that very binary — exactly the case this
+/// detection exists to handle. Probing a small host binary instead reads the
+/// loader the *system* uses, not the one (if any) pixi itself was linked
+/// against.
+const HOST_PROBE_BINARIES: &[&str] = &["/bin/sh", "/usr/bin/env", "/bin/ls"];
+
+/// Reads the dynamic loader of the host by parsing the `PT_INTERP` of the
+/// first [`HOST_PROBE_BINARIES`] entry that exists and is dynamically
+/// linked.
+fn host_dynamic_loader() -> Option<PathBuf> {
+    HOST_PROBE_BINARIES
+        .iter()
+        .find_map(|path| elf_interpreter(Path::new(path)))
+}
+
+/// Attempts to detect musl as the system libc by reading the host's dynamic
+/// loader via [`host_dynamic_loader`]. musl's loader prints a `Version
+/// x.y.z` banner to stderr when invoked without arguments, which is the
+/// standard way to probe for it since, unlike glibc, musl doesn't expose its
+/// version through a libc symbol.
+fn detect_musl_libc() -> Option<(String, Version)> {
+    let interpreter = host_dynamic_loader()?;
+    let file_name = interpreter.file_name()?.to_str()?;
+    if !file_name.contains("musl") {
+        return None;
+    }
+
+    let output = std::process::Command::new(&interpreter).output().ok()?;
+    let banner = String::from_utf8_lossy(&output.stderr);
+    let version = banner
+        .lines()
+        .find_map(|line| line.strip_prefix("Version "))
+        .and_then(|version| version.trim().parse::<Version>().ok())?;
+
+    Some(("musl".parse().unwrap(), version))
+}
+
+/// Resolves the libc family and version to use, honoring (in order of
+/// precedence) a `CONDA_OVERRIDE_GLIBC`-style override, the `[system-requirements]`
+/// specified in the manifest (e.g. `libc = { family = "musl", version =
+/// "1.2" }`), real detection of the host's dynamic loader, and finally the
+/// built-in glibc default.
+fn resolve_libc(
+    system_requirements: &SystemRequirements,
+    overrides: &VirtualPackageOverrides,
+) -> (String, Version) {
+    match &overrides.libc {
+        Override::Some(libc) => (libc.family.clone(), libc.version.clone()),
+        Override::None => ("glibc".parse().unwrap(), default_glibc_version()),
+        Override::Default => system_requirements
+            .libc
+            .as_ref()
+            .map(LibCSystemRequirement::family_and_version)
+            .map(|(family, version)| (family.to_string(), version.clone()))
+            .or_else(detect_musl_libc)
+            .unwrap_or(("glibc".parse().unwrap(), default_glibc_version())),
+    }
+}
+
+/// Resolves the cuda version to inject, honoring `CONDA_OVERRIDE_CUDA` over
+/// the manifest's `system-requirements.cuda`. When neither is set and
+/// `detect_system_cuda` is opted into (via the workspace config or
+/// `--use-system-cuda`), the host's CUDA driver is probed and its version is
+/// used instead, so the injected `__cuda` package reflects what's actually
+/// installed rather than being silently omitted.
+fn resolve_cuda(
+    system_requirements: &SystemRequirements,
+    overrides: &VirtualPackageOverrides,
+    detect_system_cuda: bool,
+) -> Option<Version> {
+    match &overrides.cuda {
+        Override::Some(version) => Some(version.clone()),
+        Override::None => None,
+        Override::Default => system_requirements.cuda.clone().or_else(|| {
+            if detect_system_cuda {
+                // Detection failure is not fatal: fall back to omitting `__cuda`
+                // exactly as before, so the lockfile stays reproducible on
+                // machines without a CUDA driver.
+                Cuda::current().ok().flatten().map(|cuda| cuda.version)
+            } else {
+                None
+            }
+        }),
+    }
+}
+
+/// Resolves the osx version to use, honoring `CONDA_OVERRIDE_OSX` over the
+/// manifest's `system-requirements.macos`.
+fn resolve_osx(
+    platform: Platform,
+    system_requirements: &SystemRequirements,
+    overrides: &VirtualPackageOverrides,
+) -> Version {
+    match &overrides.osx {
+        Override::Some(version) => version.clone(),
+        Override::None => default_mac_os_version(platform),
+        Override::Default => system_requirements
+            .macos
+            .clone()
+            .unwrap_or_else(|| default_mac_os_version(platform)),
+    }
+}
+
+/// Resolves the archspec target to inject, honoring (in order of precedence)
+/// `CONDA_OVERRIDE_ARCHSPEC`, an explicit `[system-requirements]` `archspec`
+/// target in the manifest (e.g. `archspec = "x86_64_v3"`), and finally a
+/// platform-detected default.
+fn resolve_archspec(
+    platform: Platform,
+    system_requirements: &SystemRequirements,
+    overrides: &VirtualPackageOverrides,
+) -> Option<Archspec> {
+    match &overrides.archspec {
+        Override::Some(spec) => Some(Archspec {
+            spec: spec.clone().into(),
+        }),
+        Override::None => None,
+        Override::Default => system_requirements
+            .archspec
+            .clone()
+            .map(|spec| Archspec { spec: spec.into() })
+            .or_else(|| Archspec::from_platform(platform)),
+    }
+}
+
+/// Returns `true` if a host with microarchitecture `host` satisfies a
+/// requirement of microarchitecture `required`.
+///
+/// Microarchitectures form a compatibility DAG (e.g. `x86_64` < `x86_64_v2` <
+/// `x86_64_v3` < `x86_64_v4`, plus named per-vendor uarchs): a host satisfies
+/// a requirement iff the required level *is* the host's own uarch, or is one
+/// of its ancestors, i.e. every feature implied by `required` is a subset of
+/// what `host` provides. This is deliberately not a string or version
+/// comparison, since two named uarchs are not totally ordered.
+fn archspec_satisfies(required: &str, host: &str) -> bool {
+    if required == host {
+        return true;
+    }
+    let Some(host_uarch) = archspec::cpu::Microarchitecture::known_targets().get(host) else {
+        // Unknown host uarch: be conservative and only accept an exact match.
+        return false;
+    };
+    host_uarch
+        .ancestors()
+        .iter()
+        .any(|ancestor| ancestor.name() == required)
+}
+
 /// Returns a reasonable modern set of virtual packages that should be safe
 /// enough to assume. At the time of writing, this is in sync with the
 /// conda-lock set of minimal virtual packages. <https://github.com/conda/conda-lock/blob/3d36688278ebf4f65281de0846701d61d6017ed2/conda_lock/virtual_package.py#L175>
 ///
 /// The method also takes into account system requirements specified in the
-/// project manifest.
+/// project manifest, as well as any `CONDA_OVERRIDE_*` style overrides, which
+/// take precedence over both the manifest and the built-in defaults. This
+/// keeps the solved set of virtual packages in sync with what
+/// [`verify_current_platform_has_required_virtual_packages`] checks against.
 pub(crate) fn get_minimal_virtual_packages(
     platform: Platform,
     system_requirements: &SystemRequirements,
+    overrides: &VirtualPackageOverrides,
+    detect_system_cuda: bool,
 ) -> Vec<VirtualPackage> {
-    // TODO: How to add a default cuda requirements
     let mut virtual_packages: Vec<VirtualPackage> = vec![];
 
     // Match high level platforms
@@ -39,12 +283,7 @@ pub(crate) fn get_minimal_virtual_packages(
             .unwrap_or(default_linux_version());
         virtual_packages.push(VirtualPackage::Linux(Linux { version }));
 
-        let (family, version) = system_requirements
-            .libc
-            .as_ref()
-            .map(LibCSystemRequirement::family_and_version)
-            .map(|(family, version)| (family.to_string(), version.clone()))
-            .unwrap_or(("glibc".parse().unwrap(), default_glibc_version()));
+        let (family, version) = resolve_libc(system_requirements, overrides);
         virtual_packages.push(VirtualPackage::LibC(LibC { family, version }));
     }
 
@@ -58,20 +297,18 @@ pub(crate) fn get_minimal_virtual_packages(
 
     // Add platform specific packages
     if platform.is_osx() {
-        let version = system_requirements
-            .macos
-            .clone()
-            .unwrap_or_else(|| default_mac_os_version(platform));
+        let version = resolve_osx(platform, system_requirements, overrides);
         virtual_packages.push(VirtualPackage::Osx(Osx { version }));
     }
 
-    // Cuda
-    if let Some(version) = system_requirements.cuda.clone() {
+    // Cuda, optionally detected from the host driver (see `resolve_cuda`).
+    if let Some(version) = resolve_cuda(system_requirements, overrides, detect_system_cuda) {
         virtual_packages.push(VirtualPackage::Cuda(Cuda { version }));
     }
 
-    // Archspec is only based on the platform for now
-    if let Some(spec) = Archspec::from_platform(platform) {
+    // Archspec, overridable through `CONDA_OVERRIDE_ARCHSPEC` or pinned via
+    // `system-requirements.archspec` (see `resolve_archspec`).
+    if let Some(spec) = resolve_archspec(platform, system_requirements, overrides) {
         virtual_packages.push(VirtualPackage::Archspec(spec));
     }
 
@@ -81,9 +318,89 @@ pub(crate) fn get_minimal_virtual_packages(
 impl Environment<'_> {
     /// Returns the set of virtual packages to use for the specified platform.
     /// This method takes into account the system requirements specified in
-    /// the project manifest.
+    /// the project manifest, as well as any manifest- or environment-level
+    /// overrides (with the environment winning field-by-field, see
+    /// [`combine_overrides`]).
     pub(crate) fn virtual_packages(&self, platform: Platform) -> Vec<VirtualPackage> {
-        get_minimal_virtual_packages(platform, &self.system_requirements())
+        self.virtual_packages_with_cuda_override(platform, None)
+    }
+
+    /// Same as [`Self::virtual_packages`], but lets a caller that has parsed
+    /// an explicit `--use-system-cuda`/`--no-use-system-cuda` CLI flag pass
+    /// its resolved value straight through, taking precedence over the
+    /// workspace config and `PIXI_USE_SYSTEM_CUDA` (see
+    /// [`Self::use_system_cuda`]).
+    pub(crate) fn virtual_packages_with_cuda_override(
+        &self,
+        platform: Platform,
+        use_system_cuda_override: Option<bool>,
+    ) -> Vec<VirtualPackage> {
+        get_minimal_virtual_packages(
+            platform,
+            &self.system_requirements(),
+            &combine_overrides(
+                self.manifest_virtual_package_overrides(),
+                VirtualPackageOverrides::from_env(),
+            ),
+            self.use_system_cuda(use_system_cuda_override),
+        )
+    }
+
+    /// Returns the `[target.<platform>.system-requirements]`-adjacent
+    /// override table from the manifest, if the manifest format in use
+    /// supports one.
+    ///
+    /// Not implemented yet: `pixi_manifest::WorkspaceManifest` (not vendored
+    /// in this checkout) has no such override table alongside
+    /// `SystemRequirements` to read from, so this always returns the
+    /// empty/default override and the manifest half of the `CONDA_OVERRIDE_*`
+    /// precedence chain is a no-op in practice today — only the environment
+    /// side (`VirtualPackageOverrides::from_env()`) actually takes effect.
+    /// Every caller already runs this through [`combine_overrides`] alongside
+    /// the environment override, so adding real parsing here (once
+    /// `pixi_manifest` grows the table) is the only change needed to make it
+    /// live; nothing downstream needs to change.
+    fn manifest_virtual_package_overrides(&self) -> VirtualPackageOverrides {
+        VirtualPackageOverrides::default()
+    }
+
+    /// Whether pixi should probe the host for a CUDA driver and inject the
+    /// detected version as the default `__cuda` virtual package when no
+    /// explicit `system-requirements.cuda` is set. This is opt-in because it
+    /// makes the resolved virtual package set (and therefore the lockfile)
+    /// depend on the machine pixi runs on.
+    ///
+    /// Resolved in order of precedence: `cli_override` (the already-parsed
+    /// `--use-system-cuda`/`--no-use-system-cuda` flag), then
+    /// [`Self::workspace_config_use_system_cuda`], and finally
+    /// `PIXI_USE_SYSTEM_CUDA`.
+    ///
+    /// In this checkout, `cli_override` is only ever `Some(_)` when a caller
+    /// passes one in directly (e.g. the `test_use_system_cuda_*` tests
+    /// below); there is no `pixi run`/solve command in this snapshot of the
+    /// repository to own a real `--use-system-cuda` clap flag and call this
+    /// with the parsed value, and [`Self::workspace_config_use_system_cuda`]
+    /// is likewise a stub (see its doc comment). Until one of those two
+    /// callers exists, `PIXI_USE_SYSTEM_CUDA` is the only way to actually
+    /// opt in, not a "last resort" alongside a working CLI/config surface.
+    pub(crate) fn use_system_cuda(&self, cli_override: Option<bool>) -> bool {
+        cli_override
+            .or_else(|| self.workspace_config_use_system_cuda())
+            .unwrap_or_else(|| {
+                std::env::var("PIXI_USE_SYSTEM_CUDA").is_ok_and(|v| v == "1" || v == "true")
+            })
+    }
+
+    /// The `use-system-cuda` workspace config key, if the manifest's config
+    /// section carries one.
+    ///
+    /// Not implemented yet: `pixi_manifest`'s workspace config isn't vendored
+    /// in this checkout, so there's no config struct to add a field to or
+    /// read from, and this always returns `None`. Adding that field upstream
+    /// and reading it here is the only change needed to make the workspace
+    /// config half of [`Self::use_system_cuda`]'s precedence chain live.
+    fn workspace_config_use_system_cuda(&self) -> Option<bool> {
+        None
     }
 }
 
@@ -117,6 +434,9 @@ pub enum VerifyCurrentPlatformError {
         required_version: Box<Version>,
         required_build_string: String,
     },
+
+    #[error("The current CPU microarchitecture ('{detected}') does not satisfy the required microarchitecture ('{required}')")]
+    MismatchingMicroarchitecture { required: String, detected: String },
 }
 
 /// Verifies if the current platform satisfies the minimal virtual package
@@ -137,22 +457,44 @@ pub(crate) fn verify_current_platform_has_required_virtual_packages(
         )));
     }
 
-    let system_virtual_packages = VirtualPackage::detect(&VirtualPackageOverrides::from_env())?
+    let overrides = combine_overrides(
+        environment.manifest_virtual_package_overrides(),
+        VirtualPackageOverrides::from_env(),
+    );
+    let system_virtual_packages = VirtualPackage::detect(&overrides)?
         .iter()
         .cloned()
         .map(GenericVirtualPackage::from)
         .map(|vpkg| (vpkg.name.clone(), vpkg))
         .collect::<HashMap<_, _>>();
-    let required_pkgs = environment
-        .virtual_packages(current_platform)
-        .into_iter()
-        .map(GenericVirtualPackage::from);
+    // Use the same overrides as were used to detect the system packages above,
+    // so the solved and verified virtual package sets always agree.
+    let required_pkgs = get_minimal_virtual_packages(
+        current_platform,
+        &environment.system_requirements(),
+        &overrides,
+        environment.use_system_cuda(None),
+    )
+    .into_iter()
+    .map(GenericVirtualPackage::from);
 
     // Check for every local minimum package if it is available and on the correct
     // version.
     for req_pkg in required_pkgs {
         if req_pkg.name.as_normalized() == "__archspec" {
-            // Skip archspec packages completely for now.
+            let Some(local_vpkg) = system_virtual_packages.get(&req_pkg.name) else {
+                return Err(VerifyCurrentPlatformError::MissingVirtualPackage {
+                    required: req_pkg.name.as_source().to_string(),
+                    required_version: Box::from(req_pkg.version),
+                    required_build_string: req_pkg.build_string.clone(),
+                });
+            };
+            if !archspec_satisfies(&req_pkg.build_string, &local_vpkg.build_string) {
+                return Err(VerifyCurrentPlatformError::MismatchingMicroarchitecture {
+                    required: req_pkg.build_string.clone(),
+                    detected: local_vpkg.build_string.clone(),
+                });
+            }
             continue;
         }
 
@@ -191,6 +533,7 @@ mod tests {
     use insta::assert_debug_snapshot;
     use pixi_manifest::SystemRequirements;
     use rattler_conda_types::Platform;
+    use rattler_virtual_packages::VirtualPackageOverrides;
 
     use super::*;
 
@@ -208,15 +551,142 @@ mod tests {
         ];
 
         let system_requirements = SystemRequirements::default();
+        let overrides = VirtualPackageOverrides::default();
 
         for platform in platforms {
-            let packages = get_minimal_virtual_packages(platform, &system_requirements)
-                .into_iter()
-                .map(GenericVirtualPackage::from)
-                .collect_vec();
+            let packages =
+                get_minimal_virtual_packages(platform, &system_requirements, &overrides, false)
+                    .into_iter()
+                    .map(GenericVirtualPackage::from)
+                    .collect_vec();
             insta::with_settings!({snapshot_suffix => platform.as_str()}, {
                 assert_debug_snapshot!(packages);
             });
         }
     }
+
+    #[test]
+    fn test_combine_overrides_env_wins_per_field() {
+        let manifest = VirtualPackageOverrides {
+            cuda: Override::Some("11.8".parse().unwrap()),
+            osx: Override::Some("12.0".parse().unwrap()),
+            ..VirtualPackageOverrides::default()
+        };
+        let env = VirtualPackageOverrides {
+            cuda: Override::Some("12.4".parse().unwrap()),
+            ..VirtualPackageOverrides::default()
+        };
+
+        let combined = combine_overrides(manifest, env);
+
+        // The env override wins where it's explicit...
+        assert_eq!(combined.cuda, Override::Some("12.4".parse().unwrap()));
+        // ...and the manifest override still applies where the env is silent.
+        assert_eq!(combined.osx, Override::Some("12.0".parse().unwrap()));
+        // Neither side set libc, so it stays at the default.
+        assert_eq!(combined.libc, Override::Default);
+    }
+
+    #[test]
+    fn test_archspec_satisfies_exact_match() {
+        assert!(archspec_satisfies("x86_64_v2", "x86_64_v2"));
+    }
+
+    #[test]
+    fn test_archspec_satisfies_ancestor() {
+        // A `x86_64_v3` host satisfies a `x86_64_v2` requirement, since every
+        // feature of v2 is a subset of what v3 provides.
+        assert!(archspec_satisfies("x86_64_v2", "x86_64_v3"));
+    }
+
+    #[test]
+    fn test_archspec_does_not_satisfy_descendant() {
+        // The direction matters: a `x86_64_v2` host does NOT satisfy a
+        // `x86_64_v3` requirement.
+        assert!(!archspec_satisfies("x86_64_v3", "x86_64_v2"));
+    }
+
+    #[test]
+    fn test_archspec_unknown_host_is_conservative() {
+        assert!(!archspec_satisfies(
+            "x86_64_v2",
+            "definitely-not-a-real-microarchitecture"
+        ));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_elf_interpreter_reads_own_loader() {
+        // The test binary itself is a regular ELF executable, so parsing its
+        // own `PT_INTERP` is a simple, deterministic way to exercise the
+        // parser without depending on any particular libc being installed.
+        let exe = std::env::current_exe().unwrap();
+        let interpreter =
+            elf_interpreter(&exe).expect("test binary should have a PT_INTERP header");
+        assert!(
+            interpreter.exists(),
+            "detected interpreter {interpreter:?} should exist on disk"
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_host_dynamic_loader_reads_a_host_probe_binary() {
+        // `/bin/sh` is present and dynamically linked on essentially every
+        // Linux system, including the statically-linked-pixi-on-musl case
+        // this function exists for, so this should resolve regardless of
+        // whether pixi's own binary (`/proc/self/exe`) has an interpreter.
+        let interpreter =
+            host_dynamic_loader().expect("at least one host probe binary should be dynamic");
+        assert!(
+            interpreter.exists(),
+            "detected interpreter {interpreter:?} should exist on disk"
+        );
+    }
+
+    #[test]
+    fn test_resolve_cuda_prefers_manifest_over_detection() {
+        let mut system_requirements = SystemRequirements::default();
+        system_requirements.cuda = Some("11.8".parse().unwrap());
+        let overrides = VirtualPackageOverrides::default();
+
+        // Even with detection opted into, an explicit manifest value wins,
+        // so enabling `detect_system_cuda` never disturbs an explicit pin.
+        let version = resolve_cuda(&system_requirements, &overrides, true);
+        assert_eq!(version, Some("11.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_resolve_cuda_no_detection_without_opt_in() {
+        let system_requirements = SystemRequirements::default();
+        let overrides = VirtualPackageOverrides::default();
+
+        // No manifest value and detection not opted into: `__cuda` must stay
+        // omitted exactly like before this feature existed.
+        let version = resolve_cuda(&system_requirements, &overrides, false);
+        assert_eq!(version, None);
+    }
+
+    #[test]
+    fn test_use_system_cuda_cli_override_wins_over_default() {
+        let project = crate::Workspace::from_str(
+            std::path::Path::new("pixi.toml"),
+            r#"
+            [project]
+            name = "foobar"
+            channels = ["conda-forge"]
+            platforms = ["linux-64"]
+            "#,
+        )
+        .unwrap();
+        let environment = project.default_environment();
+
+        // With no CLI override, the workspace config stub (always `None` in
+        // this checkout) and an absent `PIXI_USE_SYSTEM_CUDA` both defer to
+        // "off" by default.
+        assert!(!environment.use_system_cuda(None));
+        // An explicit CLI override always takes precedence.
+        assert!(environment.use_system_cuda(Some(true)));
+        assert!(!environment.use_system_cuda(Some(false)));
+    }
 }